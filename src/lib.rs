@@ -3,55 +3,119 @@
 //! it also provides a vmodule setting string parser. The settings string is a comma-separated list
 //! of MODULE=LEVEL key value paris.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[cfg(test)]
 #[macro_use]
 extern crate slog;
 
+use regex::Regex;
 use slog::{Drain, Key, Level, OwnedKVList, Record, Result, Serializer, KV};
 
 /// Comma-separted list of MODULE=LEVEL key value paris to configure module log level settings
 #[derive(Debug, Clone)]
 pub struct ModLevelFilterConfig(pub String);
 
+/// Parse a single level token (case insensitive) into a `slog::Level`.
+fn parse_level(level: &str) -> Option<Level> {
+    match level.to_uppercase().as_str() {
+        "TRACE" => Some(Level::Trace),
+        "DEBUG" => Some(Level::Debug),
+        "INFO" => Some(Level::Info),
+        "WARN" | "WARNING" => Some(Level::Warning),
+        "ERR" | "ERROR" => Some(Level::Error),
+        "CRIT" | "CRITICAL" => Some(Level::Critical),
+        _ => None,
+    }
+}
+
+/// A module key is treated as a pattern (rather than a literal module path) when it contains any
+/// character outside the set valid in a Rust module path (`[A-Za-z0-9_:]`).
+fn is_pattern(module: &str) -> bool {
+    !module
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b':')
+}
+
+/// A parsed `ModLevelFilterConfig`, holding an optional global default level set by a bare
+/// directive (e.g. `warn` in `warn,foo=debug`) alongside the per-module `ModLevelMap` and any
+/// pattern-keyed filters (`net::.*=trace`). Patterns preserve declaration order so matching is a
+/// deterministic first-declared-wins.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedModLevelConfig {
+    pub default: Option<Level>,
+    pub filters: ModLevelMap,
+    pub patterns: Vec<(Regex, Level)>,
+}
+
+impl ModLevelFilterConfig {
+    /// Parse the settings string, splitting bare default-level directives from `module=level`
+    /// overrides and compiling pattern-keyed entries into anchored regexes. Matches how
+    /// `RUST_LOG=info,foo=debug` works: a segment without `=` sets the default level (last one
+    /// wins), while `module=level` segments populate the map or pattern list. Entries with an
+    /// unknown level are ignored, but a module key that looks like a pattern yet fails to compile
+    /// is reported as an error rather than silently discarded.
+    pub fn parse(&self) -> std::result::Result<ParsedModLevelConfig, regex::Error> {
+        self.parse_with(true)
+    }
+
+    /// Shared parsing routine. When `strict`, an invalid regex pattern aborts with an error;
+    /// otherwise the offending entry is skipped and the remaining entries are kept.
+    fn parse_with(&self, strict: bool) -> std::result::Result<ParsedModLevelConfig, regex::Error> {
+        let mut parsed = ParsedModLevelConfig::default();
+        for kv in self.0.split(',') {
+            if let [module, level] = kv.splitn(2, '=').collect::<Vec<&str>>().as_slice() {
+                let level = match parse_level(level) {
+                    Some(level) => level,
+                    None => continue,
+                };
+                if is_pattern(module) {
+                    match Regex::new(&format!("^(?:{})$", module)) {
+                        Ok(re) => parsed.patterns.push((re, level)),
+                        Err(e) if strict => return Err(e),
+                        Err(_) => continue,
+                    }
+                } else {
+                    parsed.filters.insert(module.to_string(), level);
+                }
+            } else if let Some(level) = parse_level(kv) {
+                parsed.default = Some(level);
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
 /// Parse into the HashMap ModLevelFilter needed
 impl Into<HashMap<String, Level>> for ModLevelFilterConfig {
     fn into(self) -> HashMap<String, Level> {
-        let mut map = HashMap::<String, Level>::new();
-        self.0
-            .split(',')
-            .map(|kv: &str| {
-                if let [module, level] = kv.splitn(2, '=').collect::<Vec<&str>>().as_slice() {
-                    let slog_level = match level.to_uppercase().as_str() {
-                        "TRACE" => Some(Level::Trace),
-                        "DEBUG" => Some(Level::Debug),
-                        "INFO" => Some(Level::Info),
-                        "WARN" | "WARNING" => Some(Level::Warning),
-                        "ERR" | "ERROR" => Some(Level::Error),
-                        "CRIT" | "CRITICAL" => Some(Level::Critical),
-                        _ => None,
-                    };
-                    if let Some(level) = slog_level {
-                        map.insert(module.to_string(), level);
-                    }
-                }
-            })
-            .for_each(drop);
+        let parsed: ParsedModLevelConfig = self.into();
+        parsed.filters
+    }
+}
 
-        map
+/// Parse into the richer `ParsedModLevelConfig`. This infallible conversion skips individual
+/// entries with invalid regex patterns while keeping every valid literal, pattern and the bare
+/// default; use [`ModLevelFilterConfig::parse`] to surface invalid patterns as errors instead.
+impl From<ModLevelFilterConfig> for ParsedModLevelConfig {
+    fn from(config: ModLevelFilterConfig) -> ParsedModLevelConfig {
+        // `parse_with(false)` never returns `Err`, so the unwrap cannot panic.
+        config.parse_with(false).unwrap()
     }
 }
 
+/// Collects the string values of a configurable set of keys in a single pass over a logger's
+/// values. Keys not in `keys` are ignored.
 struct ModLevelSerializer {
-    mod_key: String,
-    val: Option<String>,
+    keys: Vec<String>,
+    values: HashMap<String, String>,
 }
 
 impl Serializer for ModLevelSerializer {
     fn emit_str(&mut self, key: Key, val: &str) -> Result {
-        if key == self.mod_key {
-            self.val = Some(val.to_string());
+        if self.keys.iter().any(|k| key == *k) {
+            self.values.insert(key.to_string(), val.to_string());
         }
         Ok(())
     }
@@ -61,6 +125,17 @@ impl Serializer for ModLevelSerializer {
     }
 }
 
+/// A structured-field filter paired with a key: a record passes this filter only if its value for
+/// the key is in `allowed` and the record meets `level`.
+#[derive(Debug, Clone)]
+pub struct ValueLevelFilter {
+    pub allowed: HashSet<String>,
+    pub level: Level,
+}
+
+/// Map of logger-value key to its [`ValueLevelFilter`].
+pub type ValueFilterMap = HashMap<String, ValueLevelFilter>;
+
 pub type ModLevelMap = HashMap<String, Level>;
 
 /// `Drain` filtering records by `Record` logging level. If the record's emitter logger has module
@@ -73,6 +148,8 @@ pub struct ModLevelFilter<D: Drain> {
     mod_key: String,
     default_level: Level,
     filters: ModLevelMap,
+    patterns: Vec<(Regex, Level)>,
+    value_filters: ValueFilterMap,
 }
 
 impl<D: Drain> std::panic::UnwindSafe for ModLevelFilter<D> {}
@@ -85,8 +162,74 @@ impl<'a, D: Drain> ModLevelFilter<D> {
             mod_key,
             default_level,
             filters,
+            patterns: Vec::new(),
+            value_filters: ValueFilterMap::new(),
         }
     }
+
+    /// Attach structured-field value filters. A record must satisfy every configured key's
+    /// [`ValueLevelFilter`] in addition to the module-level check. Composes with module filtering
+    /// rather than replacing it.
+    pub fn with_value_filters(mut self, value_filters: ValueFilterMap) -> Self {
+        self.value_filters = value_filters;
+        self
+    }
+
+    /// Build a filter from a parsed config, applying the config's bare default-level directive when
+    /// present and otherwise falling back to `default_level`.
+    pub fn from_config(
+        drain: D,
+        mod_key: String,
+        default_level: Level,
+        config: ModLevelFilterConfig,
+    ) -> Self {
+        let parsed: ParsedModLevelConfig = config.into();
+        ModLevelFilter {
+            drain,
+            mod_key,
+            default_level: parsed.default.unwrap_or(default_level),
+            filters: parsed.filters,
+            patterns: parsed.patterns,
+            value_filters: ValueFilterMap::new(),
+        }
+    }
+
+    /// Name of the environment variable read by [`ModLevelFilter::from_env_default`].
+    pub const DEFAULT_ENV_VAR: &'static str = "RUST_LOG";
+
+    /// Build a filter from the conventional `RUST_LOG` environment variable (see
+    /// [`Self::DEFAULT_ENV_VAR`]). Convenience wrapper around [`Self::from_env`].
+    pub fn from_env_default(
+        drain: D,
+        mod_key: String,
+        default_level: Level,
+    ) -> std::result::Result<Self, regex::Error> {
+        Self::from_env(drain, mod_key, default_level, Self::DEFAULT_ENV_VAR)
+    }
+
+    /// Build a filter from an environment variable (the ubiquitous `RUST_LOG` startup pattern).
+    /// `var_name` selects the variable to read — pass [`Self::DEFAULT_ENV_VAR`] for the conventional
+    /// name or any other name to override it; [`Self::from_env_default`] wraps the common case. A
+    /// missing or empty variable yields a filter with no overrides. The variable's contents are run
+    /// through the [`ModLevelFilterConfig`] parser, so malformed regex directives are reported as an
+    /// error rather than silently dropped.
+    pub fn from_env(
+        drain: D,
+        mod_key: String,
+        default_level: Level,
+        var_name: &str,
+    ) -> std::result::Result<Self, regex::Error> {
+        let config = ModLevelFilterConfig(std::env::var(var_name).unwrap_or_default());
+        let parsed = config.parse()?;
+        Ok(ModLevelFilter {
+            drain,
+            mod_key,
+            default_level: parsed.default.unwrap_or(default_level),
+            filters: parsed.filters,
+            patterns: parsed.patterns,
+            value_filters: ValueFilterMap::new(),
+        })
+    }
 }
 
 impl<'a, D: Drain> Drain for ModLevelFilter<D> {
@@ -99,20 +242,61 @@ impl<'a, D: Drain> Drain for ModLevelFilter<D> {
         logger_values: &OwnedKVList,
     ) -> std::result::Result<Self::Ok, Self::Err> {
         let mut level = self.default_level;
-        if !self.filters.is_empty() {
+        if !self.filters.is_empty() || !self.patterns.is_empty() || !self.value_filters.is_empty() {
             // If there's no module level config, skip iterating the logger_values. In this
-            // case it becomes a `slog::LevelFilter`
+            // case it becomes a `slog::LevelFilter`. Configured value filters also need the
+            // logger_values, so they trigger the pass as well. Collect the module key together
+            // with every configured value-filter key in a single pass.
+            let mut keys = Vec::with_capacity(1 + self.value_filters.len());
+            keys.push(self.mod_key.to_owned());
+            keys.extend(self.value_filters.keys().cloned());
             let mut ser = ModLevelSerializer {
-                mod_key: self.mod_key.to_owned(),
-                val: None,
+                keys,
+                values: HashMap::new(),
             };
             logger_values.serialize(record, &mut ser).unwrap();
 
-            if let Some(ref mod_name) = ser.val {
-                // Logger has a module name
-                if let Some(mod_level) = self.filters.get(mod_name) {
-                    // Filter has log level setting for logger module
-                    level = *mod_level;
+            // Every configured value filter must pass: the record's value for the key must be in
+            // the allowed set and the record must meet that key's level threshold.
+            for (key, value_filter) in &self.value_filters {
+                let passes = match ser.values.get(key) {
+                    Some(val) => {
+                        value_filter.allowed.contains(val)
+                            && record.level().is_at_least(value_filter.level)
+                    }
+                    None => false,
+                };
+                if !passes {
+                    return Ok(None);
+                }
+            }
+
+            if let Some(mod_name) = ser.values.get(&self.mod_key) {
+                // Logger has a module name. Following the `RUST_LOG` convention, search the config
+                // for the longest (most specific) `::`-delimited prefix of the module name and use
+                // its level. `"a::b::c"` tries `"a::b::c"`, then `"a::b"`, then `"a"`.
+                let mut candidate = mod_name.as_str();
+                let mut matched = false;
+                loop {
+                    if let Some(mod_level) = self.filters.get(candidate) {
+                        level = *mod_level;
+                        matched = true;
+                        break;
+                    }
+                    match candidate.rfind("::") {
+                        Some(idx) => candidate = &candidate[..idx],
+                        None => break,
+                    }
+                }
+
+                if !matched {
+                    // No literal prefix matched, fall back to pattern filters (first declared wins)
+                    for (re, pattern_level) in &self.patterns {
+                        if re.is_match(mod_name) {
+                            level = *pattern_level;
+                            break;
+                        }
+                    }
                 }
             }
         }
@@ -134,7 +318,7 @@ mod tests {
     use std::io;
     use std::sync::{Arc, Mutex};
 
-    use super::{ModLevelFilter, ModLevelFilterConfig};
+    use super::{ModLevelFilter, ModLevelFilterConfig, ParsedModLevelConfig};
     use slog::{Drain, Level, Logger, OwnedKVList, Record};
 
     const YES: &str = "YES";
@@ -203,6 +387,98 @@ mod tests {
         assert_eq!(map.len(), 0);
     }
 
+    #[test]
+    fn test_vmodule_config_default() {
+        // Bare directive sets the default, `module=level` still populates the map
+        let parsed: ParsedModLevelConfig =
+            ModLevelFilterConfig("info,foo=debug".to_string()).into();
+        assert_eq!(parsed.default, Some(Level::Info));
+        assert_eq!(parsed.filters.len(), 1);
+        assert_eq!(parsed.filters.get("foo"), Some(&Level::Debug));
+
+        // No bare directive leaves the default unset
+        let parsed: ParsedModLevelConfig = ModLevelFilterConfig("foo=debug".to_string()).into();
+        assert_eq!(parsed.default, None);
+        assert_eq!(parsed.filters.len(), 1);
+
+        // Last bare directive wins
+        let parsed: ParsedModLevelConfig = ModLevelFilterConfig("info,warn".to_string()).into();
+        assert_eq!(parsed.default, Some(Level::Warning));
+        assert_eq!(parsed.filters.len(), 0);
+    }
+
+    #[test]
+    fn test_vmodule_config_pattern() {
+        // Pattern-keyed entries compile into the pattern list, literals stay in the map
+        let parsed = ModLevelFilterConfig("net::.*=trace,foo=debug".to_string())
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.filters.len(), 1);
+        assert_eq!(parsed.filters.get("foo"), Some(&Level::Debug));
+        assert_eq!(parsed.patterns.len(), 1);
+        assert!(parsed.patterns[0].0.is_match("net::tcp"));
+        assert!(!parsed.patterns[0].0.is_match("other::net"));
+        assert_eq!(parsed.patterns[0].1, Level::Trace);
+
+        // Invalid regex is reported as an error instead of silently dropped
+        assert!(ModLevelFilterConfig("*::db=warn".to_string()).parse().is_err());
+    }
+
+    #[test]
+    fn test_from_config_default() {
+        let out = Arc::new(Mutex::new(vec![]));
+        let drain = StringDrain {
+            output: out.clone(),
+        };
+
+        // The config's bare `info` directive becomes the effective default, overriding the
+        // `Level::Critical` passed as the fallback default.
+        let filter = ModLevelFilter::from_config(
+            drain,
+            "module".to_owned(),
+            Level::Critical,
+            ModLevelFilterConfig("info,foo=debug".to_string()),
+        )
+        .fuse();
+
+        let root_log = Logger::root(filter.fuse(), o!());
+        let foo_log = root_log.new(o!("module" => "foo"));
+
+        debug!(root_log, "NO: below effective default Info");
+        info!(root_log, "YES: meets effective default Info");
+        debug!(foo_log, "YES: foo overridden to Debug");
+
+        assert_eq!(out.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_vmodule_filter_pattern() {
+        let out = Arc::new(Mutex::new(vec![]));
+        let drain = StringDrain {
+            output: out.clone(),
+        };
+
+        // A pattern lowers the level for `net::udp`, but the literal `net::tcp` wins over the
+        // competing `net::.*` pattern for the `net::tcp` logger.
+        let filter = ModLevelFilter::from_config(
+            drain,
+            "module".to_owned(),
+            Level::Warning,
+            ModLevelFilterConfig("net::.*=debug,net::tcp=error".to_string()),
+        )
+        .fuse();
+
+        let root_log = Logger::root(filter.fuse(), o!());
+        let udp_log = root_log.new(o!("module" => "net::udp"));
+        let tcp_log = root_log.new(o!("module" => "net::tcp"));
+
+        debug!(udp_log, "YES: pattern net::.* lowers net::udp to Debug");
+        debug!(tcp_log, "NO: literal net::tcp=error wins over pattern");
+        error!(tcp_log, "YES: meets literal net::tcp level");
+
+        assert_eq!(out.lock().unwrap().len(), 2);
+    }
+
     #[test]
     fn test_vmodule_filter() {
         let out = Arc::new(Mutex::new(vec![]));
@@ -250,4 +526,92 @@ mod tests {
 
         assert_eq!(out.lock().unwrap().len(), 9);
     }
+
+    #[test]
+    fn test_value_filter() {
+        use std::collections::HashSet;
+
+        use super::{ValueFilterMap, ValueLevelFilter};
+
+        let out = Arc::new(Mutex::new(vec![]));
+        let drain = StringDrain {
+            output: out.clone(),
+        };
+
+        // Only records whose `tenant` is `acme` and at least Info level pass.
+        let mut value_filters = ValueFilterMap::new();
+        value_filters.insert(
+            "tenant".to_owned(),
+            ValueLevelFilter {
+                allowed: ["acme".to_owned()].iter().cloned().collect::<HashSet<_>>(),
+                level: Level::Info,
+            },
+        );
+        let filter = ModLevelFilter::new(drain, "module".to_owned(), Level::Trace, HashMap::new())
+            .with_value_filters(value_filters)
+            .fuse();
+
+        let root_log = Logger::root(filter.fuse(), o!());
+        let acme_log = root_log.new(o!("tenant" => "acme"));
+        let other_log = root_log.new(o!("tenant" => "globex"));
+
+        info!(root_log, "NO: filtered, no tenant value");
+        debug!(acme_log, "NO: filtered, below tenant level threshold");
+        info!(acme_log, "YES: unfiltered, allowed tenant meets level");
+        error!(other_log, "NO: filtered, tenant not allowed");
+
+        assert_eq!(out.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_from_env() {
+        std::env::set_var("SLOG_VMODULE_TEST_LOG", "info,foo=debug");
+
+        let out = Arc::new(Mutex::new(vec![]));
+        let drain = StringDrain {
+            output: out.clone(),
+        };
+
+        // `default_level` is Critical but the env string's bare `info` directive overrides it.
+        let filter = ModLevelFilter::from_env(
+            drain,
+            "module".to_owned(),
+            Level::Critical,
+            "SLOG_VMODULE_TEST_LOG",
+        )
+        .unwrap()
+        .fuse();
+
+        let root_log = Logger::root(filter.fuse(), o!());
+        let foo_log = root_log.new(o!("module" => "foo"));
+
+        debug!(root_log, "NO: below env-provided default Info");
+        info!(root_log, "YES: meets env-provided default Info");
+        debug!(foo_log, "YES: foo overridden to Debug");
+
+        assert_eq!(out.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_from_env_default_var() {
+        std::env::set_var("RUST_LOG", "foo=debug");
+
+        let out = Arc::new(Mutex::new(vec![]));
+        let drain = StringDrain {
+            output: out.clone(),
+        };
+
+        let filter =
+            ModLevelFilter::from_env_default(drain, "module".to_owned(), Level::Warning)
+                .unwrap()
+                .fuse();
+
+        let root_log = Logger::root(filter.fuse(), o!());
+        let foo_log = root_log.new(o!("module" => "foo"));
+
+        debug!(foo_log, "YES: RUST_LOG set foo to Debug");
+        debug!(root_log, "NO: default level Warning");
+
+        assert_eq!(out.lock().unwrap().len(), 1);
+    }
 }